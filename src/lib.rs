@@ -3,12 +3,20 @@ use crate::sender::Sender;
 use crate::shared_state::SharedState;
 use parking_lot::RwLock;
 use std::collections::VecDeque;
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 
 pub mod receiver;
 pub mod sender;
 mod shared_state;
 
+/// Capacity of the underlying `async_broadcast` channel used to wake receivers that are
+/// already caught up. This is unrelated to the replay history capacity configured via
+/// `ReplayChannel::bounded`; it only bounds how many live notifications may be buffered
+/// per receiver. Beyond that the oldest notifications are overwritten, and a receiver
+/// that missed them reads the retained history from the shared deque instead.
+const BROADCAST_CAPACITY: usize = 1024;
+
 /// A `ReplayChannel` provides a multi-receiver, message-passing communication channel
 /// where receivers can "catch up" by receiving all previously sent messages before
 /// continuing to receive new messages.
@@ -21,6 +29,18 @@ mod shared_state;
 /// late-joining receivers need to process all past messages to be properly synchronized
 /// with the current state.
 ///
+/// By default a `ReplayChannel` retains its entire history forever. Use
+/// [`ReplayChannel::bounded`] to cap retained history to a fixed number of messages,
+/// mirroring the ring-buffer behavior of `tokio::sync::broadcast`; receivers that fall
+/// behind the retained window are told how many messages they skipped via
+/// [`receiver::RecvError::Lagged`]. Once every `Sender` has been dropped and a receiver
+/// has drained all retained messages, `receive` resolves to
+/// [`receiver::RecvError::Closed`] instead of hanging forever.
+///
+/// With the `stream` feature enabled, [`receiver::Receiver`] also implements
+/// `futures::Stream`, so it composes with `StreamExt` and `tokio_stream` instead of a
+/// manual `loop { receive().await }`.
+///
 /// # Examples
 ///
 /// Creating a `ReplayChannel` and sending messages:
@@ -32,19 +52,19 @@ mod shared_state;
 /// # rt.block_on(async { // Hidden line
 /// let replay_channel = ReplayChannel::new();
 /// let sender = replay_channel.sender();
-/// sender.send("message 1");
-/// sender.send("message 2");
+/// sender.send("message 1").await;
+/// sender.send("message 2").await;
 ///
 /// let mut receiver = replay_channel.receiver();
-/// assert_eq!(receiver.receive().await, "message 1");
-/// assert_eq!(receiver.receive().await, "message 2");
+/// assert_eq!(receiver.receive().await, Ok("message 1"));
+/// assert_eq!(receiver.receive().await, Ok("message 2"));
 ///
 /// let mut new_receiver = replay_channel.receiver();
-/// assert_eq!(new_receiver.receive().await, "message 1");
-/// assert_eq!(new_receiver.receive().await, "message 2");
+/// assert_eq!(new_receiver.receive().await, Ok("message 1"));
+/// assert_eq!(new_receiver.receive().await, Ok("message 2"));
 ///
-/// sender.send("message 3");
-/// assert_eq!(new_receiver.receive().await, "message 3");
+/// sender.send("message 3").await;
+/// assert_eq!(new_receiver.receive().await, Ok("message 3"));
 /// # }); // Hidden line
 /// ```
 pub struct ReplayChannel<T: Clone + Send + 'static> {
@@ -52,10 +72,36 @@ pub struct ReplayChannel<T: Clone + Send + 'static> {
 }
 
 impl<T: Clone + Send + Sync + 'static> ReplayChannel<T> {
+    /// Creates a channel that retains its entire message history for replay.
     pub fn new() -> Self {
+        Self::with_capacity(None)
+    }
+
+    /// Creates a channel that retains at most `capacity` messages. Once full, sending a
+    /// new message evicts the oldest retained one. Receivers that haven't yet read an
+    /// evicted message learn how many they skipped via `RecvError::Lagged` the next time
+    /// they call `receive`.
+    pub fn bounded(capacity: usize) -> Self {
+        Self::with_capacity(Some(capacity))
+    }
+
+    fn with_capacity(capacity: Option<usize>) -> Self {
+        let (mut sender, receiver) = async_broadcast::broadcast(BROADCAST_CAPACITY);
+        sender.set_overflow(true);
+        // `send` must tolerate "no active receiver right now" as a normal case (the
+        // `VecDeque` in `SharedState` is the real source of truth), not block waiting for one.
+        sender.set_await_active(false);
+        // Without a receiver kept alive, `async-broadcast` closes the channel the moment
+        // this constructor returns (nothing is subscribed yet), and every future `send`
+        // would fail permanently. An inactive receiver keeps it open at no runtime cost.
+        let inactive_receiver = receiver.deactivate();
         let shared_state = Arc::new(RwLock::new(SharedState {
             messages: VecDeque::new(),
-            notifiers: vec![],
+            base_offset: 0,
+            capacity,
+            sender,
+            _inactive_receiver: inactive_receiver,
+            sender_count: AtomicUsize::new(0),
         }));
         ReplayChannel { shared_state }
     }
@@ -67,11 +113,19 @@ impl<T: Clone + Send + Sync + 'static> ReplayChannel<T> {
     pub fn receiver(&self) -> Receiver<T> {
         Receiver::new(Arc::clone(&self.shared_state))
     }
+
+    /// Returns the most recently sent message, or `None` if nothing has been sent yet,
+    /// without creating a `Receiver`. Mirrors `tokio::sync::watch::Receiver::borrow` for
+    /// callers that only care about the current value rather than the full history.
+    pub fn borrow(&self) -> Option<T> {
+        self.shared_state.read().messages.back().cloned()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::receiver::RecvError;
 
     #[tokio::test]
     async fn message_sending_and_receiving() {
@@ -79,11 +133,11 @@ mod tests {
         let sender = channel.sender();
         let mut receiver = channel.receiver();
 
-        sender.send(1);
-        sender.send(2);
+        sender.send(1).await;
+        sender.send(2).await;
 
-        assert_eq!(receiver.receive().await, 1);
-        assert_eq!(receiver.receive().await, 2);
+        assert_eq!(receiver.receive().await, Ok(1));
+        assert_eq!(receiver.receive().await, Ok(2));
     }
 
     #[tokio::test]
@@ -93,17 +147,17 @@ mod tests {
         let mut receiver1 = channel.receiver();
 
         // Send two messages
-        sender.send(1);
-        sender.send(2);
+        sender.send(1).await;
+        sender.send(2).await;
 
         // Receiver 1 receives the two messages
-        assert_eq!(receiver1.receive().await, 1);
-        assert_eq!(receiver1.receive().await, 2);
+        assert_eq!(receiver1.receive().await, Ok(1));
+        assert_eq!(receiver1.receive().await, Ok(2));
 
         // Receiver 2 is created and should receive the same two messages
         let mut receiver2 = channel.receiver();
-        assert_eq!(receiver2.receive().await, 1);
-        assert_eq!(receiver2.receive().await, 2);
+        assert_eq!(receiver2.receive().await, Ok(1));
+        assert_eq!(receiver2.receive().await, Ok(2));
 
         // Do not call receive() again to avoid blocking
     }
@@ -115,17 +169,17 @@ mod tests {
         let mut receiver1 = channel.receiver();
         let mut receiver2 = channel.receiver();
 
-        sender.send(1);
-        sender.send(2);
+        sender.send(1).await;
+        sender.send(2).await;
 
-        assert_eq!(receiver1.receive().await, 1);
-        assert_eq!(receiver1.receive().await, 2);
-        assert_eq!(receiver2.receive().await, 1);
-        assert_eq!(receiver2.receive().await, 2);
+        assert_eq!(receiver1.receive().await, Ok(1));
+        assert_eq!(receiver1.receive().await, Ok(2));
+        assert_eq!(receiver2.receive().await, Ok(1));
+        assert_eq!(receiver2.receive().await, Ok(2));
 
-        sender.send(3);
-        assert_eq!(receiver1.receive().await, 3);
-        assert_eq!(receiver2.receive().await, 3);
+        sender.send(3).await;
+        assert_eq!(receiver1.receive().await, Ok(3));
+        assert_eq!(receiver2.receive().await, Ok(3));
     }
 
     #[tokio::test]
@@ -135,13 +189,15 @@ mod tests {
         let sender2 = channel.sender();
         let mut receiver = channel.receiver();
 
-        sender1.send(1);
-        sender2.send(2);
+        sender1.send(1).await;
+        sender2.send(2).await;
 
         let received1 = receiver.receive().await;
         let received2 = receiver.receive().await;
 
-        assert!(received1 == 1 && received2 == 2 || received1 == 2 && received2 == 1);
+        assert!(
+            received1 == Ok(1) && received2 == Ok(2) || received1 == Ok(2) && received2 == Ok(1)
+        );
     }
 
     #[tokio::test]
@@ -150,13 +206,13 @@ mod tests {
         let sender = channel.sender();
         let mut receiver = channel.receiver();
 
-        sender.send(1);
-        sender.send(2);
-        sender.send(3);
+        sender.send(1).await;
+        sender.send(2).await;
+        sender.send(3).await;
 
-        assert_eq!(receiver.receive().await, 1);
-        assert_eq!(receiver.receive().await, 2);
-        assert_eq!(receiver.receive().await, 3);
+        assert_eq!(receiver.receive().await, Ok(1));
+        assert_eq!(receiver.receive().await, Ok(2));
+        assert_eq!(receiver.receive().await, Ok(3));
     }
 
     #[tokio::test]
@@ -165,12 +221,146 @@ mod tests {
         let sender = channel.sender();
         let mut receiver = channel.receiver();
 
-        sender.send(1);
-        assert_eq!(receiver.receive().await, 1);
+        sender.send(1).await;
+        assert_eq!(receiver.receive().await, Ok(1));
+
+        sender.send(2).await;
+        sender.send(3).await;
+        assert_eq!(receiver.receive().await, Ok(2));
+        assert_eq!(receiver.receive().await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn bounded_channel_evicts_oldest_and_reports_lag() {
+        let channel = ReplayChannel::bounded(2);
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        sender.send(1).await;
+        sender.send(2).await;
+        sender.send(3).await; // evicts `1`
+
+        assert_eq!(receiver.receive().await, Err(RecvError::Lagged(1)));
+        assert_eq!(receiver.receive().await, Ok(2));
+        assert_eq!(receiver.receive().await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn receive_closes_once_all_senders_are_dropped() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        sender.send(1).await;
+        drop(sender);
+
+        assert_eq!(receiver.receive().await, Ok(1));
+        assert_eq!(receiver.receive().await, Err(RecvError::Closed));
+    }
+
+    #[tokio::test]
+    async fn latest_skips_backlog_and_returns_most_recent_message() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        assert_eq!(receiver.latest(), None);
+        assert!(receiver.is_caught_up());
+
+        sender.send(1).await;
+        sender.send(2).await;
+        sender.send(3).await;
+
+        assert!(!receiver.is_caught_up());
+        assert_eq!(receiver.latest(), Some(3));
+        assert!(receiver.is_caught_up());
+
+        sender.send(4).await;
+        assert_eq!(receiver.receive().await, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn channel_borrow_reads_latest_without_a_receiver() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+
+        assert_eq!(channel.borrow(), None);
+
+        sender.send(1).await;
+        sender.send(2).await;
+
+        assert_eq!(channel.borrow(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn resubscribe_continues_from_current_position() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        sender.send(1).await;
+        sender.send(2).await;
+        assert_eq!(receiver.receive().await, Ok(1));
+
+        let mut follower = receiver.resubscribe();
+        sender.send(3).await;
+
+        assert_eq!(follower.receive().await, Ok(2));
+        assert_eq!(follower.receive().await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn replay_from_start_always_starts_at_the_beginning() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        sender.send(1).await;
+        sender.send(2).await;
+        assert_eq!(receiver.receive().await, Ok(1));
+        assert_eq!(receiver.receive().await, Ok(2));
+
+        let mut replayed = receiver.replay_from_start();
+        assert_eq!(replayed.receive().await, Ok(1));
+        assert_eq!(replayed.receive().await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn unbounded_channel_does_not_report_false_lag_past_notification_capacity() {
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        // Nearly twice `BROADCAST_CAPACITY` notification slots. `new()` retains
+        // history forever, so none of this should ever be reported as lagged.
+        for i in 0..2_000u32 {
+            sender.send(i).await;
+        }
+
+        for i in 0..2_000u32 {
+            assert_eq!(receiver.receive().await, Ok(i));
+        }
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn stream_replays_backlog_then_delivers_live_messages() {
+        use futures::StreamExt;
+
+        let channel = ReplayChannel::new();
+        let sender = channel.sender();
+        let mut receiver = channel.receiver();
+
+        sender.send(1).await;
+        sender.send(2).await;
+
+        assert_eq!(receiver.next().await, Some(Ok(1)));
+        assert_eq!(receiver.next().await, Some(Ok(2)));
+
+        sender.send(3).await;
+        assert_eq!(receiver.next().await, Some(Ok(3)));
 
-        sender.send(2);
-        sender.send(3);
-        assert_eq!(receiver.receive().await, 2);
-        assert_eq!(receiver.receive().await, 3);
+        drop(sender);
+        assert_eq!(receiver.next().await, None);
     }
 }
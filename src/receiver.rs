@@ -1,36 +1,224 @@
-use std::ops::Add;
 use crate::shared_state::SharedState;
+use parking_lot::RwLock;
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Release};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize};
-use std::sync::atomic::Ordering::{AcqRel, Acquire};
+#[cfg(feature = "stream")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Error returned by `Receiver::receive`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RecvError {
+    /// Every `Sender` has been dropped and all retained messages have been received;
+    /// no further messages will ever arrive.
+    Closed,
+    /// The receiver fell behind and this many messages were skipped before it could
+    /// read them. The receiver's position has been fast-forwarded past the gap, so the
+    /// next call to `receive` resumes from there.
+    Lagged(u64),
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Closed => write!(f, "all senders have been dropped"),
+            RecvError::Lagged(skipped) => write!(f, "receiver lagged by {skipped} messages"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
 
 pub struct Receiver<T> {
-    shared_state: Arc<SharedState<T>>,
+    shared_state: Arc<RwLock<SharedState<T>>>,
     broadcast_receiver: async_broadcast::Receiver<T>,
-    index: AtomicUsize,
+    index: AtomicU64,
+    /// Offset below which messages must be read from `shared_state.messages` (the
+    /// deque) rather than `broadcast_receiver`. Starts at the offset this receiver
+    /// subscribed at, since messages before that predate the subscription and were
+    /// never buffered in `broadcast_receiver`. Also advances to the current tail
+    /// whenever `broadcast_receiver`'s buffer is discarded — by a lag skip past
+    /// evicted messages, or by the 1024-slot notification ring overflowing — because
+    /// at that point `broadcast_receiver` no longer holds a contiguous view of what
+    /// was sent and everything up to the tail must come from the deque instead. This
+    /// is deliberately based on physical retention (`base_offset`/`messages.len()`),
+    /// not a fixed point in time, since eviction can advance `base_offset` past it.
+    subscribe_offset: u64,
 }
 
 impl<T: Clone + Send + Sync + 'static> Receiver<T> {
-    pub async fn receive(&mut self) -> T {
-        let index = self.index.load(Acquire);
-        if index < self.shared_state.messages.len() {
-            // safely increment the index and return the message
-            self.index.fetch_add(1, AcqRel);
-            return self.shared_state.messages[index].clone()
+    pub async fn receive(&mut self) -> Result<T, RecvError> {
+        loop {
+            if let Some(result) = self.try_recv_now() {
+                return result;
+            }
+            match self.broadcast_receiver.recv().await {
+                Ok(message) => {
+                    self.index.fetch_add(1, AcqRel);
+                    return Ok(message);
+                }
+                Err(async_broadcast::RecvError::Closed) => return Err(RecvError::Closed),
+                Err(async_broadcast::RecvError::Overflowed(_)) => {
+                    // The notification ring overflowed, but everything up to the
+                    // current tail is still retained in `shared_state.messages` (see
+                    // `resync_to_tail`) — resync and retry, rather than reporting a
+                    // lag for data that wasn't actually lost.
+                    self.resync_to_tail();
+                }
+            }
         }
-        self.broadcast_receiver.recv().await.expect("broadcast receiver should not be dropped")
     }
 
-    pub(crate) fn new(shared_state: Arc<SharedState<T>>) -> Self {
+    /// Non-blocking core shared by `receive` and the `Stream` impl: serves whatever is
+    /// immediately available (skipped backlog, pre-subscription history, or an already
+    /// buffered broadcast message) and returns `None` only when the caller must actually
+    /// wait on `broadcast_receiver` for the next message.
+    fn try_recv_now(&mut self) -> Option<Result<T, RecvError>> {
+        loop {
+            let index = self.index.load(Acquire);
+            let base_offset = self.shared_state.read().base_offset;
+            if index < base_offset {
+                let skipped = base_offset - index;
+                self.index.store(base_offset, Release);
+                self.resync_to_tail();
+                return Some(Err(RecvError::Lagged(skipped)));
+            }
+            if index < self.subscribe_offset {
+                let state = self.shared_state.read();
+                let local = (index - state.base_offset) as usize;
+                self.index.fetch_add(1, AcqRel);
+                return Some(Ok(state.messages[local].clone()));
+            }
+            match self.broadcast_receiver.try_recv() {
+                Ok(message) => {
+                    self.index.fetch_add(1, AcqRel);
+                    return Some(Ok(message));
+                }
+                Err(async_broadcast::TryRecvError::Overflowed(_)) => {
+                    self.resync_to_tail();
+                }
+                Err(async_broadcast::TryRecvError::Closed) => return Some(Err(RecvError::Closed)),
+                Err(async_broadcast::TryRecvError::Empty) => {
+                    if self.shared_state.read().sender_count.load(Acquire) == 0 {
+                        return Some(Err(RecvError::Closed));
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Called after a lag skip or a broadcast-ring overflow. `BROADCAST_CAPACITY` (the
+    /// notification ring) is unrelated to the replay capacity configured via
+    /// `ReplayChannel::bounded`, so neither condition means messages were actually
+    /// lost from `shared_state.messages` — only that `broadcast_receiver` can no
+    /// longer be trusted to hold a contiguous view of what's left to read. Discards
+    /// whatever it still has buffered and marks everything up to the current tail as
+    /// servable straight from the deque instead.
+    fn resync_to_tail(&mut self) {
+        let tail = {
+            let state = self.shared_state.read();
+            state.base_offset + state.messages.len() as u64
+        };
+        self.drain_broadcast_backlog();
+        self.subscribe_offset = self.subscribe_offset.max(tail);
+    }
+
+    /// Drains and discards whatever `broadcast_receiver` currently has buffered. Needed
+    /// whenever `index` or `subscribe_offset` jumps forward instead of advancing one
+    /// message at a time (a lag skip, a ring overflow, or `latest`), so the live cursor
+    /// doesn't later redeliver a message already accounted for by the jump.
+    fn drain_broadcast_backlog(&mut self) {
+        while self.broadcast_receiver.try_recv().is_ok() {}
+    }
+
+    /// Skips straight to the tail of the message history, discarding any unread backlog,
+    /// and returns the most recently sent message (or `None` if nothing has been sent
+    /// yet). Inspired by `tokio::sync::watch`, this lets a late joiner synchronize to the
+    /// current state without replaying everything that led up to it.
+    pub fn latest(&mut self) -> Option<T> {
+        let (tail, last) = {
+            let state = self.shared_state.read();
+            (
+                state.base_offset + state.messages.len() as u64,
+                state.messages.back().cloned(),
+            )
+        };
+        self.index.store(tail, Release);
+        self.drain_broadcast_backlog();
+        last
+    }
+
+    /// Returns whether this receiver has replayed all retained history and is now
+    /// waiting on live messages, as opposed to still catching up on the backlog.
+    pub fn is_caught_up(&self) -> bool {
+        let state = self.shared_state.read();
+        self.index.load(Acquire) == state.base_offset + state.messages.len() as u64
+    }
+
+    /// Creates a new receiver over the same channel that starts at this receiver's
+    /// *current* position rather than from the beginning, so a worker that has already
+    /// processed the backlog can hand off "continue from where I am" to another task
+    /// (e.g. fan-out after catch-up) without re-reading processed history or missing
+    /// messages sent in the gap.
+    pub fn resubscribe(&self) -> Receiver<T> {
+        Self::new_at(Arc::clone(&self.shared_state), self.index.load(Acquire))
+    }
+
+    /// Creates a new receiver over the same channel that always starts at the beginning
+    /// of the retained history, regardless of this receiver's current position.
+    pub fn replay_from_start(&self) -> Receiver<T> {
+        Self::new_at(Arc::clone(&self.shared_state), 0)
+    }
+
+    pub(crate) fn new(shared_state: Arc<RwLock<SharedState<T>>>) -> Self {
+        Self::new_at(shared_state, 0)
+    }
+
+    fn new_at(shared_state: Arc<RwLock<SharedState<T>>>, index: u64) -> Self {
+        let state = shared_state.read();
+        let broadcast_receiver = state.sender.new_receiver();
+        let subscribe_offset = state.base_offset + state.messages.len() as u64;
+        drop(state);
         Receiver {
-            shared_state: shared_state.clone(),
-            broadcast_receiver : shared_state.sender.new_receiver(),
-            index: AtomicUsize::new(0),
+            shared_state,
+            broadcast_receiver,
+            index: AtomicU64::new(index),
+            subscribe_offset,
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
+/// Adapts a `Receiver` into a `futures::Stream`, draining replayed history before
+/// delegating to the live `async_broadcast::Receiver`, so it composes with
+/// `StreamExt::next`, `filter`, `map`, `tokio_stream` utilities, and the like instead of
+/// requiring a manual `loop { receive().await }`. Gated behind the `stream` feature so
+/// the core crate keeps no mandatory dependency on `futures`.
+#[cfg(feature = "stream")]
+impl<T: Clone + Send + Sync + 'static> futures::Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
 
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match this.try_recv_now() {
+            Some(Err(RecvError::Closed)) => return Poll::Ready(None),
+            Some(result) => return Poll::Ready(Some(result)),
+            None => {}
+        }
+        match Pin::new(&mut this.broadcast_receiver).poll_next(cx) {
+            Poll::Ready(Some(message)) => {
+                this.index.fetch_add(1, AcqRel);
+                Poll::Ready(Some(Ok(message)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
 }
+
+#[cfg(test)]
+mod tests {}
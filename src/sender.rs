@@ -1,23 +1,43 @@
 use crate::shared_state::SharedState;
+use parking_lot::RwLock;
+use std::sync::atomic::Ordering::AcqRel;
 use std::sync::Arc;
 
 pub struct Sender<T> {
-    shared_state: Arc<SharedState<T>>,
+    shared_state: Arc<RwLock<SharedState<T>>>,
 }
 
 impl<T: Clone + Send + 'static> Sender<T> {
     pub async fn send(&self, message: T) {
-        {
-            self.shared_state.messages.push(message.clone());
-        }
-        self.shared_state.sender.broadcast_direct(message).await.expect("broadcast should not fail");
+        let broadcast_sender = {
+            let mut state = self.shared_state.write();
+            state.messages.push_back(message.clone());
+            if let Some(capacity) = state.capacity {
+                if state.messages.len() > capacity {
+                    state.messages.pop_front();
+                    state.base_offset += 1;
+                }
+            }
+            state.sender.clone()
+        };
+        // Broadcasting is a best-effort wake-up for currently-subscribed receivers; the
+        // `VecDeque` above is the source of truth, so sending with no active receiver
+        // (or one that's lagged off the broadcast ring) is not an error.
+        let _ = broadcast_sender.broadcast_direct(message).await;
     }
 
-    pub(crate) fn new(shared_state: Arc<SharedState<T>>) -> Self {
+    pub(crate) fn new(shared_state: Arc<RwLock<SharedState<T>>>) -> Self {
+        shared_state.read().sender_count.fetch_add(1, AcqRel);
         Sender { shared_state }
     }
 }
 
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared_state.read().sender_count.fetch_sub(1, AcqRel);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,26 @@
-use append_only_vec::AppendOnlyVec;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicUsize;
 
+/// State shared between every `Sender` and `Receiver` cloned from the same
+/// `ReplayChannel`. Access is synchronized by the `RwLock` that wraps this struct.
 pub(crate) struct SharedState<T> {
-    pub(crate) messages: AppendOnlyVec<T>,
+    /// Retained message history, oldest first.
+    pub(crate) messages: VecDeque<T>,
+    /// Absolute offset of `messages[0]`. Advances by one every time a message is
+    /// evicted to keep `messages` within `capacity`.
+    pub(crate) base_offset: u64,
+    /// Maximum number of messages to retain, or `None` to retain history forever.
+    pub(crate) capacity: Option<usize>,
     pub(crate) sender: async_broadcast::Sender<T>,
+    /// Keeps the underlying `async_broadcast` channel open even while zero `Receiver`s
+    /// are subscribed (e.g. between `ReplayChannel::new` and the first `receiver()`
+    /// call). `async-broadcast` permanently closes a channel once its last receiver,
+    /// active or inactive, is dropped; this handle is never read, only held.
+    pub(crate) _inactive_receiver: async_broadcast::InactiveReceiver<T>,
+    /// Number of live `Sender` handles. The underlying `async_broadcast::Sender` above
+    /// is kept alive for as long as `SharedState` is (every `Receiver` holds a reference
+    /// to it too), so it never reports the channel as closed on its own; this counter is
+    /// how a `Receiver` tells "no more `Sender`s will ever send" apart from "nothing has
+    /// been sent yet".
+    pub(crate) sender_count: AtomicUsize,
 }